@@ -58,8 +58,17 @@ impl WaspHlsPlayer {
         }
     }
 
+    /// Fetch the same segment twice, once as a plain request and once with
+    /// the `_HLS_msn`/`_HLS_part` query params a
+    /// `#EXT-X-SERVER-CONTROL:CAN-BLOCK-RELOAD=YES` server expects on a
+    /// blocking playlist reload (in the same shape
+    /// `MediaPlaylist::next_blocking_reload_params` produces, here pinned to
+    /// the first media sequence/part since no playlist has actually been
+    /// fetched yet), so the two can be compared against one another.
     pub fn test_seg_back_and_forth(&self) {
-        jsFetchU8(self.id, "http://127.0.0.1:8080/lowlat_vs_non_lowlat.mp4");
+        let base_url = "http://127.0.0.1:8080/lowlat_vs_non_lowlat.mp4";
+        jsFetchU8(self.id, base_url);
+        jsFetchU8(self.id, &format!("{}?_HLS_msn=0&_HLS_part=0", base_url));
     }
 }
 
@@ -0,0 +1,43 @@
+use std::{error, fmt};
+
+use aes::cipher::{block_padding::Pkcs7, BlockDecryptMut, KeyIvInit};
+
+type Aes128CbcDec = cbc::Decryptor<aes::Aes128>;
+
+/// Errors that may happen while decrypting a segment encrypted through the
+/// `AES-128` method signaled by an `#EXT-X-KEY` tag.
+#[derive(Debug)]
+pub enum DecryptionError {
+    /// The segment's byte length was not a multiple of the AES block size,
+    /// or its PKCS#7 padding was invalid.
+    InvalidPadding,
+}
+
+impl fmt::Display for DecryptionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DecryptionError::InvalidPadding => {
+                write!(f, "The encrypted segment's content or padding was invalid")
+            }
+        }
+    }
+}
+
+impl error::Error for DecryptionError {}
+
+/// Decrypt `data` into a newly allocated buffer, considering it was encrypted following the
+/// `METHOD=AES-128` scheme described by the HLS specification: the whole
+/// segment is one single payload encrypted with AES-128 in CBC mode, padded
+/// with PKCS#7.
+///
+/// `key` is the raw 16-byte content key fetched from the `#EXT-X-KEY`'s
+/// `URI` attribute and `iv` is the corresponding `SegmentInfo`'s `iv` field.
+pub(crate) fn decrypt_aes_128_cbc(
+    data: &[u8],
+    key: &[u8; 16],
+    iv: &[u8; 16],
+) -> Result<Vec<u8>, DecryptionError> {
+    Aes128CbcDec::new(key.into(), iv.into())
+        .decrypt_padded_vec_mut::<Pkcs7>(data)
+        .map_err(|_| DecryptionError::InvalidPadding)
+}
@@ -1,5 +1,5 @@
 use crate::{bindings::MediaType, utils::url::Url, Logger};
-use std::{error, fmt, io::BufRead};
+use std::{error, fmt, io::BufRead, rc::Rc};
 
 use super::utils::{
     parse_byte_range, parse_decimal_floating_point, parse_decimal_integer, parse_enumerated_string,
@@ -23,9 +23,33 @@ pub struct MediaPlaylist {
     pub map: Option<MapInfo>,
     pub segment_list: Vec<SegmentInfo>,
     pub url: Url,
-    // TODO
-    // pub server_control: ServerControl,
-    // pub part_inf: Option<f64>,
+
+    /// Keys declared through `#EXT-X-SESSION-KEY`, which, unlike
+    /// `#EXT-X-KEY`, apply to the whole Multivariant Playlist and are
+    /// exposed so that they may be pre-fetched ahead of playback.
+    pub session_keys: Vec<KeyInfo>,
+
+    /// Low-Latency HLS delivery parameters, as signaled by
+    /// `#EXT-X-SERVER-CONTROL`. Absent on playlists not opting into LL-HLS.
+    pub server_control: Option<ServerControl>,
+
+    /// The target duration of partial segments, in seconds, as signaled by
+    /// `#EXT-X-PART-INF:PART-TARGET`.
+    pub part_inf: Option<f64>,
+
+    /// The last `#EXT-X-PRELOAD-HINT` of type `PART` seen, announcing a part
+    /// that the server is still producing and that can be requested ahead of
+    /// its completion.
+    pub preload_hint: Option<PreloadHint>,
+
+    /// Parts of the segment that is still being produced by the server, and
+    /// that therefore has no `#EXTINF`/URI pair yet.
+    pub trailing_parts: Vec<PartInfo>,
+
+    /// Identifiers of `DATERANGE`s that were removed from the playlist since
+    /// the last delta update, as signaled by an `#EXT-X-SKIP`'s
+    /// `RECENTLY-REMOVED-DATERANGES` attribute.
+    pub recently_removed_dateranges: Vec<String>,
 }
 
 #[derive(Clone, Debug)]
@@ -40,6 +64,76 @@ pub struct SegmentInfo {
     pub duration: f64,
     pub byte_range: Option<ByteRange>,
     pub url: Url,
+
+    /// The encryption key applicable to this segment, if any, as signaled by
+    /// the last `#EXT-X-KEY` tag seen before it.
+    pub key: Option<Rc<KeyInfo>>,
+
+    /// The effective IV to use when decrypting this segment with `key`.
+    ///
+    /// Set whenever `key` is defined, i.e. for both the `AES-128` and
+    /// `SAMPLE-AES` methods. When the `#EXT-X-KEY` tag did not carry an explicit `IV`
+    /// attribute, this defaults to this segment's media sequence number
+    /// encoded as a 128-bit big-endian value, as mandated by the spec.
+    pub iv: Option<[u8; 16]>,
+
+    /// Partial segments this segment was announced through before being
+    /// completed, in the order they were parsed, as signaled by
+    /// `#EXT-X-PART`.
+    pub parts: Vec<PartInfo>,
+
+    /// Index of the discontinuity this segment belongs to, starting at the
+    /// playlist's `discontinuity_sequence` and incremented each time an
+    /// `#EXT-X-DISCONTINUITY` tag is encountered before it.
+    pub discontinuity: u32,
+}
+
+/// A single partial segment, as signaled by an `#EXT-X-PART` tag.
+#[derive(Clone, Debug)]
+pub struct PartInfo {
+    pub duration: f64,
+    pub url: Url,
+    pub independent: bool,
+    pub byte_range: Option<ByteRange>,
+    pub gap: bool,
+}
+
+/// A hint, signaled by `#EXT-X-PRELOAD-HINT`, that a resource (currently only
+/// `PART` is supported) is being produced by the server and can already be
+/// requested, potentially before it is fully available.
+#[derive(Clone, Debug)]
+pub struct PreloadHint {
+    pub url: Url,
+    pub byte_range_start: Option<usize>,
+    pub byte_range_length: Option<usize>,
+}
+
+/// The decryption method signaled by an `#EXT-X-KEY` or `#EXT-X-SESSION-KEY`
+/// tag's `METHOD` attribute.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyMethod {
+    /// Segments are not encrypted.
+    None,
+    /// Segments are encrypted with AES-128 in CBC mode, the whole segment
+    /// being one single encrypted payload.
+    Aes128,
+    /// Only the media data of each sample is encrypted, container metadata
+    /// is left untouched.
+    SampleAes,
+}
+
+/// A resolved `#EXT-X-KEY` (or `#EXT-X-SESSION-KEY`) tag.
+///
+/// A `#EXT-X-KEY` applies to every `SegmentInfo` parsed after it until
+/// another `#EXT-X-KEY` tag is encountered, mirroring the `available_keys`
+/// accumulator kept by the hls_m3u8 parser.
+#[derive(Clone, Debug)]
+pub struct KeyInfo {
+    pub method: KeyMethod,
+    pub uri: Option<Url>,
+    pub iv: Option<[u8; 16]>,
+    pub keyformat: Option<String>,
+    pub keyformatversions: Option<String>,
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -49,61 +143,184 @@ pub enum PlaylistType {
     None,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default)]
 pub struct StartAttribute {
     time_offset: Option<f64>,
     precise: bool,
 }
 
-// #[derive(Clone, Debug)]
-// pub struct ServerControl {
-//     can_skip_until: Option<f64>,
-//     can_skip_dateranges: bool,
-//     hold_back: u32,
-//     part_hold_back: Option<u32>,
-//     can_block_reload: bool,
-// }
+impl StartAttribute {
+    pub fn time_offset(&self) -> Option<f64> {
+        self.time_offset
+    }
+
+    pub fn is_precise(&self) -> bool {
+        self.precise
+    }
+}
+
+/// Low-Latency HLS delivery parameters signaled by `#EXT-X-SERVER-CONTROL`.
+#[derive(Clone, Debug, Default)]
+pub struct ServerControl {
+    pub can_skip_until: Option<f64>,
+    pub can_skip_dateranges: bool,
+    pub hold_back: Option<f64>,
+    pub part_hold_back: Option<f64>,
+    pub can_block_reload: bool,
+}
 
 #[derive(Clone, Debug)]
 pub struct SegmentList {
     pub inner: Vec<SegmentInfo>,
 }
 
+/// The kind of issue encountered while parsing a Media Playlist, without the
+/// positional context `MediaPlaylistParsingError` wraps it in.
 #[derive(Debug)]
-pub enum MediaPlaylistParsingError {
+pub enum MediaPlaylistParsingErrorKind {
+    /// The underlying `BufRead` failed to produce a line (I/O error, or
+    /// invalid UTF-8), carrying that error's message along.
+    Io(String),
     UnparsableExtInf,
     UnparsableByteRange,
     UriMissingInMap,
     MissingTargetDuration,
     UriWithoutExtInf,
+    UnparsableKey,
+    UnparsableServerControl,
+    UnparsablePartInf,
+    UnparsablePart,
+    UriMissingInPart,
+    UnparsablePreloadHint,
+    UriMissingInPreloadHint,
+    UnparsableSkip,
+    /// An `#EXT-X-SKIP` delta update was encountered, but no previously
+    /// parsed `MediaPlaylist` was given to reconstruct the skipped prefix
+    /// from. The caller should request the full (non-delta) playlist
+    /// instead and retry.
+    DeltaUpdateWithoutPriorPlaylist,
 }
 
-impl fmt::Display for MediaPlaylistParsingError {
+impl fmt::Display for MediaPlaylistParsingErrorKind {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            MediaPlaylistParsingError::UnparsableExtInf => {
+            MediaPlaylistParsingErrorKind::Io(msg) => {
+                write!(f, "Failed to read the playlist: {}", msg)
+            }
+            MediaPlaylistParsingErrorKind::UnparsableExtInf => {
                 write!(f, "One of the #EXTINF value could not be parsed")
             }
-            MediaPlaylistParsingError::UriMissingInMap => {
+            MediaPlaylistParsingErrorKind::UriMissingInMap => {
                 write!(f, "An #EXT-X-MAP was missing its mandatory URI attribute")
             }
-            MediaPlaylistParsingError::MissingTargetDuration => {
+            MediaPlaylistParsingErrorKind::MissingTargetDuration => {
                 write!(f, "Missing mandatory TARGETDURATION attribute")
             }
-            MediaPlaylistParsingError::UriWithoutExtInf => {
+            MediaPlaylistParsingErrorKind::UriWithoutExtInf => {
                 write!(f, "One of the uri was not linked to any #EXTINF")
             }
-            MediaPlaylistParsingError::UnparsableByteRange => {
+            MediaPlaylistParsingErrorKind::UnparsableByteRange => {
                 write!(f, "One of the uri had an Unparsable BYTERANGE")
             }
+            MediaPlaylistParsingErrorKind::UnparsableKey => {
+                write!(f, "One of the #EXT-X-KEY or #EXT-X-SESSION-KEY tags was malformed")
+            }
+            MediaPlaylistParsingErrorKind::UnparsableServerControl => {
+                write!(f, "The #EXT-X-SERVER-CONTROL tag was malformed")
+            }
+            MediaPlaylistParsingErrorKind::UnparsablePartInf => {
+                write!(f, "The #EXT-X-PART-INF tag had a missing or unparsable PART-TARGET attribute")
+            }
+            MediaPlaylistParsingErrorKind::UnparsablePart => {
+                write!(f, "One of the #EXT-X-PART tags had a missing or unparsable DURATION, or an unparsable BYTERANGE")
+            }
+            MediaPlaylistParsingErrorKind::UriMissingInPart => {
+                write!(f, "One of the #EXT-X-PART tags was missing its mandatory URI attribute")
+            }
+            MediaPlaylistParsingErrorKind::UnparsablePreloadHint => {
+                write!(f, "The #EXT-X-PRELOAD-HINT tag had an unparsable BYTERANGE-START or BYTERANGE-LENGTH")
+            }
+            MediaPlaylistParsingErrorKind::UriMissingInPreloadHint => {
+                write!(f, "The #EXT-X-PRELOAD-HINT tag was missing its mandatory URI attribute")
+            }
+            MediaPlaylistParsingErrorKind::UnparsableSkip => {
+                write!(f, "The #EXT-X-SKIP tag was malformed, skipped more segments than available, or its MEDIA-SEQUENCE regressed relative to the prior playlist")
+            }
+            MediaPlaylistParsingErrorKind::DeltaUpdateWithoutPriorPlaylist => {
+                write!(
+                    f,
+                    "Received an #EXT-X-SKIP delta update without a prior MediaPlaylist to reconstruct it from"
+                )
+            }
+        }
+    }
+}
+
+/// An error encountered while parsing a Media Playlist, with enough
+/// positional context (`line_number`, `line`) for the JS-facing layer to
+/// surface an actionable diagnostic instead of a thread panic inside wasm.
+#[derive(Debug)]
+pub struct MediaPlaylistParsingError {
+    pub kind: MediaPlaylistParsingErrorKind,
+    /// 1-indexed number of the offending line.
+    pub line_number: usize,
+    /// The raw, offending line text. Empty when the line itself could not
+    /// be read (`Io`), or when the error was raised after all lines were
+    /// consumed (e.g. a missing mandatory tag).
+    pub line: String,
+}
+
+impl fmt::Display for MediaPlaylistParsingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.line.is_empty() {
+            write!(f, "{} (line {})", self.kind, self.line_number)
+        } else {
+            write!(f, "{} (line {}: \"{}\")", self.kind, self.line_number, self.line)
         }
     }
 }
 
 impl error::Error for MediaPlaylistParsingError {}
 
+/// A non-fatal issue encountered while parsing a Media Playlist (an
+/// unparsable optional value, or an unrecognized tag), collected alongside a
+/// successful parse instead of aborting it.
+#[derive(Debug, Clone)]
+pub struct MediaPlaylistParsingWarning {
+    /// 1-indexed number of the line the warning was raised for.
+    pub line_number: usize,
+    pub line: String,
+    pub message: String,
+}
+
+impl fmt::Display for MediaPlaylistParsingWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} (line {}: \"{}\")",
+            self.message, self.line_number, self.line
+        )
+    }
+}
+
 impl MediaPlaylist {
-    pub fn create(playlist: impl BufRead, url: Url) -> Result<Self, MediaPlaylistParsingError> {
+    /// Parse a Media Playlist.
+    ///
+    /// `prior_playlist`, when given, is the last successfully parsed
+    /// `MediaPlaylist` for that same URL. It is only read when `playlist`
+    /// turns out to be a delta update (i.e. it contains an `#EXT-X-SKIP`
+    /// tag, as requested through `_HLS_skip=YES`), in which case it is used
+    /// to reconstruct the segments the server skipped over.
+    ///
+    /// Returns, alongside the parsed playlist, every non-fatal issue that
+    /// was encountered (an unparsable optional value, or an unrecognized
+    /// tag) so the caller may surface them without aborting the parse.
+    pub fn create(
+        playlist: impl BufRead,
+        url: Url,
+        prior_playlist: Option<&MediaPlaylist>,
+    ) -> Result<(Self, Vec<MediaPlaylistParsingWarning>), MediaPlaylistParsingError> {
+        let mut warnings: Vec<MediaPlaylistParsingWarning> = vec![];
         let mut version: Option<u32> = None;
         let mut independent_segments = false;
         let mut target_duration: Option<u32> = None;
@@ -113,23 +330,47 @@ impl MediaPlaylist {
         let mut playlist_type = PlaylistType::None;
         let mut i_frames_only = false;
         let mut map: Option<MapInfo> = None;
+        let mut session_keys: Vec<KeyInfo> = vec![];
+        let mut current_key: Option<Rc<KeyInfo>> = None;
+        let mut server_control: Option<ServerControl> = None;
+        let mut part_inf: Option<f64> = None;
+        let mut preload_hint: Option<PreloadHint> = None;
+        let mut next_segment_parts: Vec<PartInfo> = vec![];
+        let mut recently_removed_dateranges: Vec<String> = vec![];
 
-        let start = StartAttribute {
-            time_offset: None,
-            precise: false,
-        };
+        let mut start = StartAttribute::default();
 
         let playlist_base_url = url.pathname();
 
         let mut curr_start_time = 0.;
+        let mut current_discontinuity = discontinuity_sequence;
         let mut segment_list: Vec<SegmentInfo> = vec![];
         let mut next_segment_duration: Option<f64> = None;
         let mut current_byte: Option<usize> = None;
         let mut next_segment_byte_range: Option<ByteRange> = None;
 
-        let lines = playlist.lines();
-        for line in lines {
-            let str_line = line.unwrap();
+        let mut last_line_number = 0;
+        for (line_idx, line) in playlist.lines().enumerate() {
+            let line_number = line_idx + 1;
+            last_line_number = line_number;
+            let str_line = line.map_err(|e| MediaPlaylistParsingError {
+                kind: MediaPlaylistParsingErrorKind::Io(e.to_string()),
+                line_number,
+                line: String::new(),
+            })?;
+            // Kept apart from `str_line`, which is moved into the segment's
+            // `Url` before `UriWithoutExtInf` may still need to be reported.
+            let line_text = str_line.clone();
+            let mkerr = |kind: MediaPlaylistParsingErrorKind| MediaPlaylistParsingError {
+                kind,
+                line_number,
+                line: line_text.clone(),
+            };
+            let mkwarn = |message: String| MediaPlaylistParsingWarning {
+                line_number,
+                line: str_line.clone(),
+                message,
+            };
             if str_line.is_empty() {
                 continue;
             } else if let Some(stripped) = str_line.strip_prefix("#EXT") {
@@ -141,22 +382,27 @@ impl MediaPlaylist {
                 match &str_line[4..colon_idx] {
                     "-X-VERSION" => match parse_decimal_integer(&str_line, colon_idx + 1).0 {
                         Ok(v) => version = Some(v as u32),
-                        Err(_) => Logger::warn("Unparsable VERSION value"),
+                        Err(_) => warnings.push(mkwarn("Unparsable VERSION value".to_owned())),
                     },
                     "-X-TARGETDURATION" => {
                         match parse_decimal_integer(&str_line, colon_idx + 1).0 {
                             Ok(t) => target_duration = Some(t as u32),
-                            Err(_) => Logger::warn("Unparsable TARGETDURATION value"),
+                            Err(_) => {
+                                warnings.push(mkwarn("Unparsable TARGETDURATION value".to_owned()))
+                            }
                         }
                     }
                     "-X-ENDLIST" => end_list = true,
                     "-X-INDEPENDENT-SEGMENTS" => independent_segments = true,
-                    "-X-START:" =>
-                        /* TODO */
-                        {}
+                    "-X-START" => {
+                        let (parsed_start, start_warnings) =
+                            parse_start_attribute_list(&str_line, colon_idx + 1);
+                        start = parsed_start;
+                        warnings.extend(start_warnings.into_iter().map(mkwarn));
+                    }
                     "INF" => match parse_decimal_floating_point(&str_line, 4 + "INF:".len()).0 {
                         Ok(d) => next_segment_duration = Some(d),
-                        Err(_) => return Err(MediaPlaylistParsingError::UnparsableExtInf),
+                        Err(_) => return Err(mkerr(MediaPlaylistParsingErrorKind::UnparsableExtInf)),
                     },
                     "-X-BYTERANGE" => {
                         match parse_byte_range(&str_line, 5 + "-X-BYTERANGE".len(), current_byte) {
@@ -165,28 +411,36 @@ impl MediaPlaylist {
                                 next_segment_byte_range = Some(br);
                             }
                             _ => {
-                                return Err(MediaPlaylistParsingError::UnparsableByteRange);
+                                return Err(mkerr(MediaPlaylistParsingErrorKind::UnparsableByteRange));
                             }
                         }
                     }
                     "-X-MEDIA-SEQUENCE" => {
                         match parse_decimal_integer(&str_line, colon_idx + 1).0 {
                             Ok(s) => media_sequence = s as u32,
-                            Err(_) => Logger::warn("Unparsable MEDIA-SEQUENCE value"),
+                            Err(_) => {
+                                warnings.push(mkwarn("Unparsable MEDIA-SEQUENCE value".to_owned()))
+                            }
                         }
                     }
                     "-X-DISCONTINUITY-SEQUENCE" => {
                         match parse_decimal_integer(&str_line, colon_idx + 1).0 {
-                            Ok(s) => discontinuity_sequence = s as u32,
-                            Err(_) => Logger::warn("Unparsable DISCONTINUITY-SEQUENCE value"),
+                            Ok(s) => {
+                                discontinuity_sequence = s as u32;
+                                current_discontinuity = discontinuity_sequence;
+                            }
+                            Err(_) => warnings.push(mkwarn(
+                                "Unparsable DISCONTINUITY-SEQUENCE value".to_owned(),
+                            )),
                         }
                     }
+                    "-X-DISCONTINUITY" => current_discontinuity += 1,
                     "-X-PLAYLIST-TYPE" => match parse_enumerated_string(&str_line, colon_idx + 1).0
                     {
                         "EVENT" => playlist_type = PlaylistType::Event,
                         "VOD" => playlist_type = PlaylistType::VoD,
                         x => {
-                            Logger::warn(&format!("Unrecognized playlist type: {}", x));
+                            warnings.push(mkwarn(format!("Unrecognized playlist type: {}", x)));
                             playlist_type = PlaylistType::None;
                         }
                     },
@@ -196,6 +450,96 @@ impl MediaPlaylist {
                         }
                     }
                     "-X-I-FRAMES-ONLY" => i_frames_only = true,
+                    "-X-KEY" => {
+                        let (key_info, key_warnings) = parse_key_attribute_list(
+                            &str_line,
+                            colon_idx + 1,
+                            playlist_base_url,
+                        )
+                        .map_err(mkerr)?;
+                        warnings.extend(key_warnings.into_iter().map(mkwarn));
+                        current_key = match key_info.method {
+                            KeyMethod::None => None,
+                            _ => Some(Rc::new(key_info)),
+                        };
+                    }
+                    "-X-SESSION-KEY" => {
+                        let (key_info, key_warnings) = parse_key_attribute_list(
+                            &str_line,
+                            colon_idx + 1,
+                            playlist_base_url,
+                        )
+                        .map_err(mkerr)?;
+                        warnings.extend(key_warnings.into_iter().map(mkwarn));
+                        session_keys.push(key_info);
+                    }
+                    "-X-SERVER-CONTROL" => {
+                        server_control = Some(
+                            parse_server_control_attribute_list(&str_line, colon_idx + 1)
+                                .map_err(mkerr)?,
+                        );
+                    }
+                    "-X-PART-INF" => {
+                        part_inf = Some(
+                            parse_part_inf_attribute_list(&str_line, colon_idx + 1)
+                                .map_err(mkerr)?,
+                        );
+                    }
+                    "-X-PART" => {
+                        let part = parse_part_attribute_list(
+                            &str_line,
+                            colon_idx + 1,
+                            playlist_base_url,
+                        )
+                        .map_err(mkerr)?;
+                        next_segment_parts.push(part);
+                    }
+                    "-X-PRELOAD-HINT" => {
+                        if let Some(hint) = parse_preload_hint_attribute_list(
+                            &str_line,
+                            colon_idx + 1,
+                            playlist_base_url,
+                        )
+                        .map_err(mkerr)?
+                        {
+                            preload_hint = Some(hint);
+                        }
+                    }
+                    "-X-SKIP" => {
+                        let (skipped_segments, removed_dateranges) =
+                            parse_skip_attribute_list(&str_line, colon_idx + 1).map_err(mkerr)?;
+                        recently_removed_dateranges = removed_dateranges;
+                        let prior = prior_playlist
+                            .ok_or_else(|| mkerr(MediaPlaylistParsingErrorKind::DeltaUpdateWithoutPriorPlaylist))?;
+                        // The delta's own MEDIA-SEQUENCE (already parsed by
+                        // this point, as it precedes EXT-X-SKIP in the
+                        // playlist) tells us where the reconstructed prefix
+                        // must start in the prior playlist's segment list.
+                        if media_sequence < prior.media_sequence {
+                            return Err(mkerr(MediaPlaylistParsingErrorKind::UnparsableSkip));
+                        }
+                        let start_idx = (media_sequence - prior.media_sequence) as usize;
+                        let end_idx = start_idx + skipped_segments as usize;
+                        let reconstructed = prior
+                            .segment_list
+                            .get(start_idx..end_idx)
+                            .ok_or_else(|| mkerr(MediaPlaylistParsingErrorKind::UnparsableSkip))?;
+                        for seg in reconstructed {
+                            curr_start_time = seg.start + seg.duration;
+                            current_discontinuity = seg.discontinuity;
+                            segment_list.push(seg.clone());
+                        }
+                        // The delta playlist's first new segment may carry a
+                        // BYTERANGE with an omitted offset, meaning
+                        // "contiguous with the previous segment's range" —
+                        // which here is one of the segments just
+                        // reconstructed above, not one parsed from this
+                        // document.
+                        current_byte = reconstructed
+                            .last()
+                            .and_then(|s| s.byte_range.as_ref())
+                            .map(|br| br.last_byte + 1);
+                    }
                     "-X-MAP" => {
                         let mut map_info_url: Option<Url> = None;
                         let mut map_info_byte_range: Option<ByteRange> = None;
@@ -206,7 +550,9 @@ impl MediaPlaylist {
                             }
                             match str_line[base_offset..].find('=') {
                                 None => {
-                                    Logger::warn("Attribute Name not followed by equal sign");
+                                    warnings.push(mkwarn(
+                                        "Attribute Name not followed by equal sign".to_owned(),
+                                    ));
                                     break;
                                 }
                                 Some(idx) => match &str_line[base_offset..base_offset + idx] {
@@ -235,13 +581,16 @@ impl MediaPlaylist {
                                                     current_byte = Some(br.last_byte + 1);
                                                     map_info_byte_range = Some(br);
                                                 }
-                                                _ => return Err(
-                                                    MediaPlaylistParsingError::UnparsableByteRange,
-                                                ),
+                                                _ => return Err(mkerr(
+                                                    MediaPlaylistParsingErrorKind::UnparsableByteRange,
+                                                )),
                                             };
                                         }
                                     }
-                                    _ => {}
+                                    _ => {
+                                        base_offset =
+                                            skip_unknown_attribute_value(&str_line, base_offset + idx + 1);
+                                    }
                                 },
                             }
                         }
@@ -251,11 +600,11 @@ impl MediaPlaylist {
                                 byte_range: map_info_byte_range,
                             });
                         } else {
-                            return Err(MediaPlaylistParsingError::UriMissingInMap);
+                            return Err(mkerr(MediaPlaylistParsingErrorKind::UriMissingInMap));
                         }
                     }
                     "M3U" => {}
-                    x => Logger::debug(&format!("Unrecognized tag: \"{}\"", x)),
+                    x => warnings.push(mkwarn(format!("Unrecognized tag: \"{}\"", x))),
                 }
             } else if str_line.starts_with('#') {
                 continue;
@@ -268,43 +617,65 @@ impl MediaPlaylist {
                     Url::from_relative(playlist_base_url, seg_url)
                 };
                 if let Some(duration) = next_segment_duration {
+                    let iv = current_key.as_ref().and_then(|key| match key.method {
+                        KeyMethod::None => None,
+                        _ => Some(key.iv.unwrap_or_else(|| {
+                            sequence_number_as_iv(media_sequence + segment_list.len() as u32)
+                        })),
+                    });
                     let seg = SegmentInfo {
                         start: curr_start_time,
                         duration,
                         byte_range: next_segment_byte_range,
                         url: seg_url,
+                        key: current_key.clone(),
+                        iv,
+                        parts: std::mem::take(&mut next_segment_parts),
+                        discontinuity: current_discontinuity,
                     };
                     segment_list.push(seg);
                     curr_start_time += duration;
                     next_segment_duration = None;
                     next_segment_byte_range = None;
                 } else {
-                    return Err(MediaPlaylistParsingError::UriWithoutExtInf);
+                    return Err(mkerr(MediaPlaylistParsingErrorKind::UriWithoutExtInf));
                 }
             }
         }
 
         let target_duration = match target_duration {
             Some(target_duration) => target_duration,
-            None => return Err(MediaPlaylistParsingError::MissingTargetDuration),
+            None => {
+                return Err(MediaPlaylistParsingError {
+                    kind: MediaPlaylistParsingErrorKind::MissingTargetDuration,
+                    line_number: last_line_number.max(1),
+                    line: String::new(),
+                })
+            }
         };
-        Ok(MediaPlaylist {
-            version,
-            independent_segments,
-            start,
-            target_duration,
-            media_sequence,
-            discontinuity_sequence,
-            end_list,
-            playlist_type,
-            i_frames_only,
-            map,
-            segment_list,
-            url,
-            // TODO
-            // server_control,
-            // part_inf,
-        })
+        Ok((
+            MediaPlaylist {
+                version,
+                independent_segments,
+                start,
+                target_duration,
+                media_sequence,
+                discontinuity_sequence,
+                end_list,
+                playlist_type,
+                i_frames_only,
+                map,
+                segment_list,
+                url,
+                session_keys,
+                server_control,
+                part_inf,
+                preload_hint,
+                trailing_parts: next_segment_parts,
+                recently_removed_dateranges,
+            },
+            warnings,
+        ))
     }
 
     pub(crate) fn extension(&self) -> Option<&str> {
@@ -382,4 +753,779 @@ impl MediaPlaylist {
     pub fn first_segment_end(&self) -> Option<f64> {
         self.segment_list.first().map(|x| x.start + x.duration)
     }
+
+    /// Resolve `start` into an absolute start time within this playlist's
+    /// segment timeline, so that `load_content` can seek there instead of
+    /// always starting at zero.
+    ///
+    /// A positive `TIME-OFFSET` is clamped so as not to exceed `duration()`;
+    /// a negative one, measured back from the end of the playlist, is
+    /// clamped so as not to precede `beginning()`. Returns `None` when no
+    /// `#EXT-X-START` tag was present, or the playlist has no segment.
+    pub fn resolved_start_time(&self) -> Option<f64> {
+        let offset = self.start.time_offset?;
+        let beginning = self.beginning()?;
+        let duration = self.duration()?;
+        if offset >= 0. {
+            Some((beginning + offset).min(duration))
+        } else {
+            Some((duration + offset).max(beginning))
+        }
+    }
+
+    /// Return the index of the discontinuity the given playback `time` (in
+    /// the playlist's own segment timeline) falls into, so the append
+    /// pipeline can set the right per-discontinuity timestamp offset on the
+    /// media source.
+    ///
+    /// Returns `None` for a playlist with no segments, or for a `time`
+    /// preceding the first segment's start.
+    pub fn discontinuity_at(&self, time: f64) -> Option<u32> {
+        let mut last_discontinuity = None;
+        for seg in self.segment_list.iter() {
+            if time < seg.start {
+                break;
+            }
+            last_discontinuity = Some(seg.discontinuity);
+        }
+        last_discontinuity
+    }
+
+    /// Build the `_HLS_msn`/`_HLS_part` query parameters that should be
+    /// appended to this playlist's URL to perform a blocking playlist
+    /// reload that only resolves once the next anticipated part is
+    /// available, per `#EXT-X-SERVER-CONTROL:CAN-BLOCK-RELOAD=YES`.
+    ///
+    /// Returns `None` when the server did not advertise support for
+    /// blocking reload, in which case the requester should fall back to a
+    /// plain reload instead.
+    pub fn next_blocking_reload_params(&self) -> Option<String> {
+        let server_control = self.server_control.as_ref()?;
+        if !server_control.can_block_reload {
+            return None;
+        }
+        let next_msn = self.media_sequence + self.segment_list.len() as u32;
+        let next_part = self.trailing_parts.len() as u32;
+        Some(format!("_HLS_msn={}&_HLS_part={}", next_msn, next_part))
+    }
+}
+
+/// Serializes this `MediaPlaylist` back into a spec-valid `.m3u8` document.
+///
+/// Emits the version, target duration, media and discontinuity sequences,
+/// playlist type, `#EXT-X-MAP`, each segment's `#EXT-X-DISCONTINUITY`,
+/// `#EXT-X-BYTERANGE`, `#EXTINF` and URI, and `#EXT-X-ENDLIST`. Encryption,
+/// LL-HLS, delta-update, `#EXT-X-START`, `#EXT-X-INDEPENDENT-SEGMENTS`,
+/// `#EXT-X-I-FRAMES-ONLY` and `#EXT-X-PROGRAM-DATE-TIME` tags are not
+/// re-emitted. This is enough to let the proxy rewrite segment URLs to local
+/// blob URLs and hand the result back to the media source.
+impl fmt::Display for MediaPlaylist {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "#EXTM3U")?;
+        if let Some(version) = self.version {
+            writeln!(f, "#EXT-X-VERSION:{}", version)?;
+        }
+        writeln!(f, "#EXT-X-TARGETDURATION:{}", self.target_duration)?;
+        writeln!(f, "#EXT-X-MEDIA-SEQUENCE:{}", self.media_sequence)?;
+        if self.discontinuity_sequence != 0 {
+            writeln!(
+                f,
+                "#EXT-X-DISCONTINUITY-SEQUENCE:{}",
+                self.discontinuity_sequence
+            )?;
+        }
+        match self.playlist_type {
+            PlaylistType::Event => writeln!(f, "#EXT-X-PLAYLIST-TYPE:EVENT")?,
+            PlaylistType::VoD => writeln!(f, "#EXT-X-PLAYLIST-TYPE:VOD")?,
+            PlaylistType::None => {}
+        }
+        if let Some(map) = &self.map {
+            match &map.byte_range {
+                Some(br) => writeln!(
+                    f,
+                    "#EXT-X-MAP:URI=\"{}\",BYTERANGE=\"{}\"",
+                    map.uri,
+                    format_byte_range(br)
+                )?,
+                None => writeln!(f, "#EXT-X-MAP:URI=\"{}\"", map.uri)?,
+            }
+        }
+        let mut current_discontinuity = self.discontinuity_sequence;
+        for seg in &self.segment_list {
+            for _ in current_discontinuity..seg.discontinuity {
+                writeln!(f, "#EXT-X-DISCONTINUITY")?;
+            }
+            current_discontinuity = seg.discontinuity;
+            if let Some(br) = &seg.byte_range {
+                writeln!(f, "#EXT-X-BYTERANGE:{}", format_byte_range(br))?;
+            }
+            writeln!(f, "#EXTINF:{},", seg.duration)?;
+            writeln!(f, "{}", seg.url)?;
+        }
+        if self.end_list {
+            writeln!(f, "#EXT-X-ENDLIST")?;
+        }
+        Ok(())
+    }
+}
+
+/// Format a `ByteRange` the way `#EXT-X-BYTERANGE`/`BYTERANGE` attributes
+/// expect it: `<length>[@<offset>]`.
+fn format_byte_range(byte_range: &ByteRange) -> String {
+    format!(
+        "{}@{}",
+        byte_range.last_byte - byte_range.first_byte + 1,
+        byte_range.first_byte
+    )
+}
+
+/// Skip past the value of an attribute whose name wasn't recognized, so
+/// that the attributes that follow it in the same comma-separated list can
+/// still be found correctly, instead of the next `=` being searched for
+/// from somewhere in the middle of that value.
+///
+/// `value_offset` must point right after the attribute's `=`. Mirrors the
+/// quoted-string vs bare-token distinction every recognized attribute value
+/// already relies on. Shared with `multivariant_playlist`, whose attribute
+/// lists follow the same grammar.
+pub(crate) fn skip_unknown_attribute_value(str_line: &str, value_offset: usize) -> usize {
+    if value_offset >= str_line.len() {
+        return value_offset;
+    }
+    if str_line[value_offset..].starts_with('"') {
+        parse_quoted_string(str_line, value_offset).1 + 1
+    } else {
+        parse_enumerated_string(str_line, value_offset).1 + 1
+    }
+}
+
+/// Parse the attribute list following a `#EXT-X-START` tag, starting right
+/// after its colon. Malformed attributes are warned about and ignored,
+/// mirroring the rest of this parser's tolerance for non-mandatory tags.
+fn parse_start_attribute_list(
+    str_line: &str,
+    mut base_offset: usize,
+) -> (StartAttribute, Vec<String>) {
+    let mut time_offset: Option<f64> = None;
+    let mut precise = false;
+    let mut warnings: Vec<String> = vec![];
+
+    loop {
+        if base_offset >= str_line.len() {
+            break;
+        }
+        match str_line[base_offset..].find('=') {
+            None => break,
+            Some(idx) => match &str_line[base_offset..base_offset + idx] {
+                "TIME-OFFSET" => {
+                    let (val, end_offset) =
+                        parse_decimal_floating_point(str_line, base_offset + idx + 1);
+                    base_offset = end_offset + 1;
+                    match val {
+                        Ok(v) => time_offset = Some(v),
+                        Err(_) => warnings.push("Unparsable TIME-OFFSET value".to_owned()),
+                    }
+                }
+                "PRECISE" => {
+                    let (val, end_offset) =
+                        parse_enumerated_string(str_line, base_offset + idx + 1);
+                    base_offset = end_offset + 1;
+                    precise = val == "YES";
+                }
+                _ => {
+                    base_offset = skip_unknown_attribute_value(str_line, base_offset + idx + 1);
+                }
+            },
+        }
+    }
+
+    (
+        StartAttribute {
+            time_offset,
+            precise,
+        },
+        warnings,
+    )
+}
+
+/// Encode `seq` as the 128-bit big-endian value used as the default IV for
+/// AES-128 when a `#EXT-X-KEY` tag does not carry an explicit `IV` attribute.
+fn sequence_number_as_iv(seq: u32) -> [u8; 16] {
+    let mut iv = [0u8; 16];
+    iv[12..].copy_from_slice(&seq.to_be_bytes());
+    iv
+}
+
+/// Parse the attribute list following a `#EXT-X-KEY` or `#EXT-X-SESSION-KEY`
+/// tag, starting right after its colon.
+fn parse_key_attribute_list(
+    str_line: &str,
+    mut base_offset: usize,
+    playlist_base_url: &str,
+) -> Result<(KeyInfo, Vec<String>), MediaPlaylistParsingErrorKind> {
+    let mut method: Option<KeyMethod> = None;
+    let mut uri: Option<Url> = None;
+    let mut iv: Option<[u8; 16]> = None;
+    let mut keyformat: Option<String> = None;
+    let mut keyformatversions: Option<String> = None;
+    let mut warnings: Vec<String> = vec![];
+
+    loop {
+        if base_offset >= str_line.len() {
+            break;
+        }
+        match str_line[base_offset..].find('=') {
+            None => {
+                warnings.push("Attribute Name not followed by equal sign".to_owned());
+                break;
+            }
+            Some(idx) => match &str_line[base_offset..base_offset + idx] {
+                "METHOD" => {
+                    let (val, end_offset) =
+                        parse_enumerated_string(str_line, base_offset + idx + 1);
+                    base_offset = end_offset + 1;
+                    method = Some(match val {
+                        "NONE" => KeyMethod::None,
+                        "AES-128" => KeyMethod::Aes128,
+                        "SAMPLE-AES" => KeyMethod::SampleAes,
+                        x => {
+                            Logger::warn(&format!("Unrecognized KEY method: {}", x));
+                            return Err(MediaPlaylistParsingErrorKind::UnparsableKey);
+                        }
+                    });
+                }
+                "URI" => {
+                    let (parsed, end_offset) = parse_quoted_string(str_line, base_offset + idx + 1);
+                    base_offset = end_offset + 1;
+                    if let Ok(val) = parsed {
+                        let key_url = Url::new(val.to_owned());
+                        let key_url = if key_url.is_absolute() {
+                            key_url
+                        } else {
+                            Url::from_relative(playlist_base_url, key_url)
+                        };
+                        uri = Some(key_url);
+                    }
+                }
+                "IV" => {
+                    let (val, end_offset) =
+                        parse_enumerated_string(str_line, base_offset + idx + 1);
+                    base_offset = end_offset + 1;
+                    iv = match parse_hexadecimal_iv(val) {
+                        Some(parsed_iv) => Some(parsed_iv),
+                        None => {
+                            warnings.push("Unparsable IV attribute".to_owned());
+                            None
+                        }
+                    };
+                }
+                "KEYFORMAT" => {
+                    let (parsed, end_offset) = parse_quoted_string(str_line, base_offset + idx + 1);
+                    base_offset = end_offset + 1;
+                    if let Ok(val) = parsed {
+                        keyformat = Some(val.to_owned());
+                    }
+                }
+                "KEYFORMATVERSIONS" => {
+                    let (parsed, end_offset) = parse_quoted_string(str_line, base_offset + idx + 1);
+                    base_offset = end_offset + 1;
+                    if let Ok(val) = parsed {
+                        keyformatversions = Some(val.to_owned());
+                    }
+                }
+                _ => {
+                    base_offset = skip_unknown_attribute_value(str_line, base_offset + idx + 1);
+                }
+            },
+        }
+    }
+
+    let method = method.unwrap_or(KeyMethod::None);
+    if method != KeyMethod::None && uri.is_none() {
+        return Err(MediaPlaylistParsingErrorKind::UnparsableKey);
+    }
+    Ok((
+        KeyInfo {
+            method,
+            uri,
+            iv,
+            keyformat,
+            keyformatversions,
+        },
+        warnings,
+    ))
+}
+
+/// Parse an `IV` attribute's value (e.g. `0X10EF...`) into its 16 raw bytes.
+fn parse_hexadecimal_iv(val: &str) -> Option<[u8; 16]> {
+    let hex = val.strip_prefix("0x").or_else(|| val.strip_prefix("0X"))?;
+    if hex.len() != 32 {
+        return None;
+    }
+    let mut iv = [0u8; 16];
+    for (i, byte) in iv.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).ok()?;
+    }
+    Some(iv)
+}
+
+/// Parse the attribute list following a `#EXT-X-SERVER-CONTROL` tag, starting
+/// right after its colon.
+fn parse_server_control_attribute_list(
+    str_line: &str,
+    mut base_offset: usize,
+) -> Result<ServerControl, MediaPlaylistParsingErrorKind> {
+    let mut server_control = ServerControl::default();
+    loop {
+        if base_offset >= str_line.len() {
+            break;
+        }
+        match str_line[base_offset..].find('=') {
+            None => break,
+            Some(idx) => match &str_line[base_offset..base_offset + idx] {
+                "CAN-SKIP-UNTIL" => {
+                    let (val, end_offset) =
+                        parse_decimal_floating_point(str_line, base_offset + idx + 1);
+                    base_offset = end_offset + 1;
+                    match val {
+                        Ok(v) => server_control.can_skip_until = Some(v),
+                        Err(_) => return Err(MediaPlaylistParsingErrorKind::UnparsableServerControl),
+                    }
+                }
+                "CAN-SKIP-DATERANGES" => {
+                    let (val, end_offset) =
+                        parse_enumerated_string(str_line, base_offset + idx + 1);
+                    base_offset = end_offset + 1;
+                    server_control.can_skip_dateranges = val == "YES";
+                }
+                "HOLD-BACK" => {
+                    let (val, end_offset) =
+                        parse_decimal_floating_point(str_line, base_offset + idx + 1);
+                    base_offset = end_offset + 1;
+                    match val {
+                        Ok(v) => server_control.hold_back = Some(v),
+                        Err(_) => return Err(MediaPlaylistParsingErrorKind::UnparsableServerControl),
+                    }
+                }
+                "PART-HOLD-BACK" => {
+                    let (val, end_offset) =
+                        parse_decimal_floating_point(str_line, base_offset + idx + 1);
+                    base_offset = end_offset + 1;
+                    match val {
+                        Ok(v) => server_control.part_hold_back = Some(v),
+                        Err(_) => return Err(MediaPlaylistParsingErrorKind::UnparsableServerControl),
+                    }
+                }
+                "CAN-BLOCK-RELOAD" => {
+                    let (val, end_offset) =
+                        parse_enumerated_string(str_line, base_offset + idx + 1);
+                    base_offset = end_offset + 1;
+                    server_control.can_block_reload = val == "YES";
+                }
+                _ => {
+                    base_offset = skip_unknown_attribute_value(str_line, base_offset + idx + 1);
+                }
+            },
+        }
+    }
+    Ok(server_control)
+}
+
+/// Parse the attribute list following a `#EXT-X-PART-INF` tag, returning its
+/// mandatory `PART-TARGET` value.
+fn parse_part_inf_attribute_list(
+    str_line: &str,
+    mut base_offset: usize,
+) -> Result<f64, MediaPlaylistParsingErrorKind> {
+    let mut part_target: Option<f64> = None;
+    loop {
+        if base_offset >= str_line.len() {
+            break;
+        }
+        match str_line[base_offset..].find('=') {
+            None => break,
+            Some(idx) => match &str_line[base_offset..base_offset + idx] {
+                "PART-TARGET" => {
+                    let (val, end_offset) =
+                        parse_decimal_floating_point(str_line, base_offset + idx + 1);
+                    base_offset = end_offset + 1;
+                    match val {
+                        Ok(v) => part_target = Some(v),
+                        Err(_) => return Err(MediaPlaylistParsingErrorKind::UnparsablePartInf),
+                    }
+                }
+                _ => {
+                    base_offset = skip_unknown_attribute_value(str_line, base_offset + idx + 1);
+                }
+            },
+        }
+    }
+    part_target.ok_or(MediaPlaylistParsingErrorKind::UnparsablePartInf)
+}
+
+/// Parse the attribute list following a `#EXT-X-PART` tag, starting right
+/// after its colon.
+fn parse_part_attribute_list(
+    str_line: &str,
+    mut base_offset: usize,
+    playlist_base_url: &str,
+) -> Result<PartInfo, MediaPlaylistParsingErrorKind> {
+    let mut duration: Option<f64> = None;
+    let mut url: Option<Url> = None;
+    let mut independent = false;
+    let mut byte_range: Option<ByteRange> = None;
+    let mut gap = false;
+
+    loop {
+        if base_offset >= str_line.len() {
+            break;
+        }
+        match str_line[base_offset..].find('=') {
+            None => break,
+            Some(idx) => match &str_line[base_offset..base_offset + idx] {
+                "DURATION" => {
+                    let (val, end_offset) =
+                        parse_decimal_floating_point(str_line, base_offset + idx + 1);
+                    base_offset = end_offset + 1;
+                    match val {
+                        Ok(v) => duration = Some(v),
+                        Err(_) => return Err(MediaPlaylistParsingErrorKind::UnparsablePart),
+                    }
+                }
+                "URI" => {
+                    let (parsed, end_offset) = parse_quoted_string(str_line, base_offset + idx + 1);
+                    base_offset = end_offset + 1;
+                    if let Ok(val) = parsed {
+                        let part_url = Url::new(val.to_owned());
+                        let part_url = if part_url.is_absolute() {
+                            part_url
+                        } else {
+                            Url::from_relative(playlist_base_url, part_url)
+                        };
+                        url = Some(part_url);
+                    }
+                }
+                "INDEPENDENT" => {
+                    let (val, end_offset) =
+                        parse_enumerated_string(str_line, base_offset + idx + 1);
+                    base_offset = end_offset + 1;
+                    independent = val == "YES";
+                }
+                "GAP" => {
+                    let (val, end_offset) =
+                        parse_enumerated_string(str_line, base_offset + idx + 1);
+                    base_offset = end_offset + 1;
+                    gap = val == "YES";
+                }
+                "BYTERANGE" => {
+                    let (parsed, end_offset) = parse_quoted_string(str_line, base_offset + idx + 1);
+                    base_offset = end_offset + 1;
+                    if let Ok(val) = parsed {
+                        match parse_byte_range(val, 0, None) {
+                            Some(br) => byte_range = Some(br),
+                            None => return Err(MediaPlaylistParsingErrorKind::UnparsablePart),
+                        }
+                    }
+                }
+                _ => {
+                    base_offset = skip_unknown_attribute_value(str_line, base_offset + idx + 1);
+                }
+            },
+        }
+    }
+
+    let duration = duration.ok_or(MediaPlaylistParsingErrorKind::UnparsablePart)?;
+    let url = url.ok_or(MediaPlaylistParsingErrorKind::UriMissingInPart)?;
+    Ok(PartInfo {
+        duration,
+        url,
+        independent,
+        byte_range,
+        gap,
+    })
+}
+
+/// Parse the attribute list following a `#EXT-X-PRELOAD-HINT` tag, starting
+/// right after its colon. Returns `None` when the hint's `TYPE` is not
+/// `PART`, the only kind currently supported.
+fn parse_preload_hint_attribute_list(
+    str_line: &str,
+    mut base_offset: usize,
+    playlist_base_url: &str,
+) -> Result<Option<PreloadHint>, MediaPlaylistParsingErrorKind> {
+    let mut hint_type: Option<&str> = None;
+    let mut url: Option<Url> = None;
+    let mut byte_range_start: Option<usize> = None;
+    let mut byte_range_length: Option<usize> = None;
+
+    loop {
+        if base_offset >= str_line.len() {
+            break;
+        }
+        match str_line[base_offset..].find('=') {
+            None => break,
+            Some(idx) => match &str_line[base_offset..base_offset + idx] {
+                "TYPE" => {
+                    let (val, end_offset) =
+                        parse_enumerated_string(str_line, base_offset + idx + 1);
+                    base_offset = end_offset + 1;
+                    hint_type = Some(val);
+                }
+                "URI" => {
+                    let (parsed, end_offset) = parse_quoted_string(str_line, base_offset + idx + 1);
+                    base_offset = end_offset + 1;
+                    if let Ok(val) = parsed {
+                        let hint_url = Url::new(val.to_owned());
+                        let hint_url = if hint_url.is_absolute() {
+                            hint_url
+                        } else {
+                            Url::from_relative(playlist_base_url, hint_url)
+                        };
+                        url = Some(hint_url);
+                    }
+                }
+                "BYTERANGE-START" => {
+                    let (val, end_offset) = parse_decimal_integer(str_line, base_offset + idx + 1);
+                    base_offset = end_offset + 1;
+                    match val {
+                        Ok(v) => byte_range_start = Some(v as usize),
+                        Err(_) => return Err(MediaPlaylistParsingErrorKind::UnparsablePreloadHint),
+                    }
+                }
+                "BYTERANGE-LENGTH" => {
+                    let (val, end_offset) = parse_decimal_integer(str_line, base_offset + idx + 1);
+                    base_offset = end_offset + 1;
+                    match val {
+                        Ok(v) => byte_range_length = Some(v as usize),
+                        Err(_) => return Err(MediaPlaylistParsingErrorKind::UnparsablePreloadHint),
+                    }
+                }
+                _ => {
+                    base_offset = skip_unknown_attribute_value(str_line, base_offset + idx + 1);
+                }
+            },
+        }
+    }
+
+    if hint_type != Some("PART") {
+        return Ok(None);
+    }
+    let url = url.ok_or(MediaPlaylistParsingErrorKind::UriMissingInPreloadHint)?;
+    Ok(Some(PreloadHint {
+        url,
+        byte_range_start,
+        byte_range_length,
+    }))
+}
+
+/// Parse the attribute list following a `#EXT-X-SKIP` tag, returning its
+/// mandatory `SKIPPED-SEGMENTS` count and the optional, tab-delimited list of
+/// IDs carried by `RECENTLY-REMOVED-DATERANGES`.
+fn parse_skip_attribute_list(
+    str_line: &str,
+    mut base_offset: usize,
+) -> Result<(u32, Vec<String>), MediaPlaylistParsingErrorKind> {
+    let mut skipped_segments: Option<u32> = None;
+    let mut removed_dateranges: Vec<String> = vec![];
+
+    loop {
+        if base_offset >= str_line.len() {
+            break;
+        }
+        match str_line[base_offset..].find('=') {
+            None => break,
+            Some(idx) => match &str_line[base_offset..base_offset + idx] {
+                "SKIPPED-SEGMENTS" => {
+                    let (val, end_offset) = parse_decimal_integer(str_line, base_offset + idx + 1);
+                    base_offset = end_offset + 1;
+                    match val {
+                        Ok(v) => skipped_segments = Some(v as u32),
+                        Err(_) => return Err(MediaPlaylistParsingErrorKind::UnparsableSkip),
+                    }
+                }
+                "RECENTLY-REMOVED-DATERANGES" => {
+                    let (parsed, end_offset) = parse_quoted_string(str_line, base_offset + idx + 1);
+                    base_offset = end_offset + 1;
+                    if let Ok(val) = parsed {
+                        removed_dateranges =
+                            val.split('\t').map(|id| id.to_owned()).collect();
+                    }
+                }
+                _ => {
+                    base_offset = skip_unknown_attribute_value(str_line, base_offset + idx + 1);
+                }
+            },
+        }
+    }
+
+    let skipped_segments = skipped_segments.ok_or(MediaPlaylistParsingErrorKind::UnparsableSkip)?;
+    Ok((skipped_segments, removed_dateranges))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn playlist_url() -> Url {
+        Url::new("http://example.com/live/index.m3u8".to_owned())
+    }
+
+    #[test]
+    fn parses_key_with_interleaved_unrecognized_attribute() {
+        let playlist = "#EXTM3U\n\
+#EXT-X-TARGETDURATION:6\n\
+#EXT-X-MEDIA-SEQUENCE:10\n\
+#EXT-X-KEY:METHOD=AES-128,VENDOR-X=foo,URI=\"key.bin\"\n\
+#EXTINF:6.0,\n\
+seg10.ts\n";
+        let (media_playlist, warnings) =
+            MediaPlaylist::create(playlist.as_bytes(), playlist_url(), None).unwrap();
+        assert!(warnings.is_empty());
+        let key = media_playlist.segment_list[0].key.as_ref().unwrap();
+        assert_eq!(key.method, KeyMethod::Aes128);
+        assert!(key.uri.is_some());
+    }
+
+    #[test]
+    fn defaults_iv_to_the_segment_media_sequence_when_not_given() {
+        let playlist = "#EXTM3U\n\
+#EXT-X-TARGETDURATION:6\n\
+#EXT-X-MEDIA-SEQUENCE:10\n\
+#EXT-X-KEY:METHOD=AES-128,URI=\"key.bin\"\n\
+#EXTINF:6.0,\n\
+seg10.ts\n";
+        let (media_playlist, _) =
+            MediaPlaylist::create(playlist.as_bytes(), playlist_url(), None).unwrap();
+        let seg = &media_playlist.segment_list[0];
+        assert_eq!(seg.iv, Some(sequence_number_as_iv(10)));
+    }
+
+    #[test]
+    fn reports_a_warning_instead_of_failing_on_unparsable_iv() {
+        let playlist = "#EXTM3U\n\
+#EXT-X-TARGETDURATION:6\n\
+#EXT-X-MEDIA-SEQUENCE:10\n\
+#EXT-X-KEY:METHOD=AES-128,URI=\"key.bin\",IV=not-hex\n\
+#EXTINF:6.0,\n\
+seg10.ts\n";
+        let (media_playlist, warnings) =
+            MediaPlaylist::create(playlist.as_bytes(), playlist_url(), None).unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(media_playlist.segment_list[0].key.as_ref().unwrap().iv, None);
+    }
+
+    #[test]
+    fn reconstructs_skipped_segments_from_the_prior_playlist() {
+        let full_playlist = "#EXTM3U\n\
+#EXT-X-TARGETDURATION:6\n\
+#EXT-X-MEDIA-SEQUENCE:0\n\
+#EXTINF:6.0,\n\
+seg0.ts\n\
+#EXTINF:6.0,\n\
+seg1.ts\n\
+#EXTINF:6.0,\n\
+seg2.ts\n";
+        let (prior, _) =
+            MediaPlaylist::create(full_playlist.as_bytes(), playlist_url(), None).unwrap();
+
+        let delta_playlist = "#EXTM3U\n\
+#EXT-X-TARGETDURATION:6\n\
+#EXT-X-MEDIA-SEQUENCE:1\n\
+#EXT-X-SKIP:SKIPPED-SEGMENTS=2\n\
+#EXTINF:6.0,\n\
+seg3.ts\n";
+        let (delta, _) =
+            MediaPlaylist::create(delta_playlist.as_bytes(), playlist_url(), Some(&prior))
+                .unwrap();
+        assert_eq!(delta.segment_list.len(), 3);
+        assert_eq!(
+            delta.segment_list[0].url.to_string(),
+            prior.segment_list[1].url.to_string()
+        );
+        assert_eq!(
+            delta.segment_list[1].url.to_string(),
+            prior.segment_list[2].url.to_string()
+        );
+        assert_eq!(delta.segment_list[2].start, prior.segment_list[2].start + 6.0);
+    }
+
+    #[test]
+    fn rejects_a_skip_delta_update_whose_media_sequence_regresses() {
+        let full_playlist = "#EXTM3U\n\
+#EXT-X-TARGETDURATION:6\n\
+#EXT-X-MEDIA-SEQUENCE:5\n\
+#EXTINF:6.0,\n\
+seg5.ts\n";
+        let (prior, _) =
+            MediaPlaylist::create(full_playlist.as_bytes(), playlist_url(), None).unwrap();
+
+        let stale_delta = "#EXTM3U\n\
+#EXT-X-TARGETDURATION:6\n\
+#EXT-X-MEDIA-SEQUENCE:0\n\
+#EXT-X-SKIP:SKIPPED-SEGMENTS=1\n\
+#EXTINF:6.0,\n\
+seg1.ts\n";
+        let err = MediaPlaylist::create(stale_delta.as_bytes(), playlist_url(), Some(&prior))
+            .unwrap_err();
+        assert!(matches!(
+            err.kind,
+            MediaPlaylistParsingErrorKind::UnparsableSkip
+        ));
+    }
+
+    #[test]
+    fn clamps_a_positive_start_time_offset_to_the_playlist_duration() {
+        let playlist = "#EXTM3U\n\
+#EXT-X-TARGETDURATION:6\n\
+#EXT-X-MEDIA-SEQUENCE:0\n\
+#EXT-X-START:TIME-OFFSET=1000\n\
+#EXTINF:6.0,\n\
+seg0.ts\n\
+#EXTINF:6.0,\n\
+seg1.ts\n";
+        let (media_playlist, _) =
+            MediaPlaylist::create(playlist.as_bytes(), playlist_url(), None).unwrap();
+        assert_eq!(media_playlist.resolved_start_time(), Some(12.0));
+    }
+
+    #[test]
+    fn clamps_a_negative_start_time_offset_to_the_playlist_beginning() {
+        let playlist = "#EXTM3U\n\
+#EXT-X-TARGETDURATION:6\n\
+#EXT-X-MEDIA-SEQUENCE:0\n\
+#EXT-X-START:TIME-OFFSET=-1000\n\
+#EXTINF:6.0,\n\
+seg0.ts\n\
+#EXTINF:6.0,\n\
+seg1.ts\n";
+        let (media_playlist, _) =
+            MediaPlaylist::create(playlist.as_bytes(), playlist_url(), None).unwrap();
+        assert_eq!(media_playlist.resolved_start_time(), Some(0.0));
+    }
+
+    #[test]
+    fn display_round_trips_through_create() {
+        let playlist = "#EXTM3U\n\
+#EXT-X-TARGETDURATION:6\n\
+#EXT-X-MEDIA-SEQUENCE:0\n\
+#EXTINF:6.0,\n\
+seg0.ts\n\
+#EXT-X-DISCONTINUITY\n\
+#EXTINF:6.0,\n\
+seg1.ts\n\
+#EXT-X-ENDLIST\n";
+        let (original, _) =
+            MediaPlaylist::create(playlist.as_bytes(), playlist_url(), None).unwrap();
+        let serialized = original.to_string();
+        let (reparsed, _) =
+            MediaPlaylist::create(serialized.as_bytes(), playlist_url(), None).unwrap();
+
+        assert_eq!(reparsed.media_sequence, original.media_sequence);
+        assert_eq!(reparsed.end_list, original.end_list);
+        assert_eq!(reparsed.segment_list.len(), original.segment_list.len());
+        assert_eq!(
+            reparsed.segment_list[1].discontinuity,
+            original.segment_list[1].discontinuity
+        );
+    }
 }
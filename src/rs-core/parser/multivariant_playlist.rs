@@ -0,0 +1,636 @@
+use crate::{utils::url::Url, Logger};
+use std::{error, fmt, io::BufRead};
+
+use super::media_playlist::skip_unknown_attribute_value;
+use super::utils::{
+    parse_decimal_floating_point, parse_decimal_integer, parse_enumerated_string,
+    parse_quoted_string,
+};
+
+/// Structure representing the concept of the `Multivariant Playlist` (also
+/// called `Master Playlist`) in HLS: the top-level playlist referencing one
+/// `Variant Stream` per quality/bitrate, plus the alternative audio,
+/// subtitles and closed-captions `Rendition`s shared across variants.
+#[derive(Clone, Debug)]
+pub struct MultivariantPlaylist {
+    pub independent_segments: bool,
+    pub variants: Vec<VariantStream>,
+    pub i_frame_variants: Vec<IFrameStreamInfo>,
+    pub renditions: Vec<Rendition>,
+    pub url: Url,
+}
+
+/// A single `#EXT-X-STREAM-INF` entry: one Media Playlist among the
+/// quality/bitrate ladder, plus the rendition groups it may rely on.
+#[derive(Clone, Debug)]
+pub struct VariantStream {
+    pub bandwidth: u64,
+    pub average_bandwidth: Option<u64>,
+    pub codecs: Option<String>,
+    pub resolution: Option<(u32, u32)>,
+    pub frame_rate: Option<f64>,
+    pub audio: Option<String>,
+    pub video: Option<String>,
+    pub subtitles: Option<String>,
+    pub url: Url,
+}
+
+/// A single `#EXT-X-I-FRAME-STREAM-INF` entry: a Media Playlist made of
+/// I-frames only, used for trick-play (e.g. scrubbing).
+#[derive(Clone, Debug)]
+pub struct IFrameStreamInfo {
+    pub bandwidth: u64,
+    pub average_bandwidth: Option<u64>,
+    pub codecs: Option<String>,
+    pub resolution: Option<(u32, u32)>,
+    pub url: Url,
+}
+
+/// The kind of media a `#EXT-X-MEDIA` tag's `TYPE` attribute refers to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RenditionType {
+    Audio,
+    Video,
+    Subtitles,
+    ClosedCaptions,
+}
+
+/// A single `#EXT-X-MEDIA` entry: an alternative audio, video, subtitles or
+/// closed-captions track, grouped by `group_id` and referenced from
+/// `VariantStream::audio`/`video`/`subtitles`.
+#[derive(Clone, Debug)]
+pub struct Rendition {
+    pub rendition_type: RenditionType,
+    pub group_id: String,
+    pub name: String,
+    pub language: Option<String>,
+    pub is_default: bool,
+    pub autoselect: bool,
+    pub uri: Option<Url>,
+}
+
+#[derive(Debug)]
+pub enum MultivariantPlaylistParsingError {
+    /// The underlying `BufRead` failed to produce a line (I/O error, or
+    /// invalid UTF-8), carrying that error's message along.
+    Io(String),
+    UnparsableStreamInf,
+    UriMissingForStreamInf,
+    UnparsableIFrameStreamInf,
+    UnparsableMedia,
+    MissingAttributeInMedia(&'static str),
+}
+
+impl fmt::Display for MultivariantPlaylistParsingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MultivariantPlaylistParsingError::Io(msg) => {
+                write!(f, "Failed to read the playlist: {}", msg)
+            }
+            MultivariantPlaylistParsingError::UnparsableStreamInf => {
+                write!(f, "One of the #EXT-X-STREAM-INF tags had a missing or unparsable mandatory BANDWIDTH attribute")
+            }
+            MultivariantPlaylistParsingError::UriMissingForStreamInf => {
+                write!(f, "One of the #EXT-X-STREAM-INF tags was not followed by a URI")
+            }
+            MultivariantPlaylistParsingError::UnparsableIFrameStreamInf => {
+                write!(f, "One of the #EXT-X-I-FRAME-STREAM-INF tags was malformed")
+            }
+            MultivariantPlaylistParsingError::UnparsableMedia => {
+                write!(f, "One of the #EXT-X-MEDIA tags had an unrecognized TYPE")
+            }
+            MultivariantPlaylistParsingError::MissingAttributeInMedia(attr) => {
+                write!(f, "One of the #EXT-X-MEDIA tags was missing its mandatory {} attribute", attr)
+            }
+        }
+    }
+}
+
+impl error::Error for MultivariantPlaylistParsingError {}
+
+impl MultivariantPlaylist {
+    pub fn create(
+        playlist: impl BufRead,
+        url: Url,
+    ) -> Result<Self, MultivariantPlaylistParsingError> {
+        let mut independent_segments = false;
+        let mut variants: Vec<VariantStream> = vec![];
+        let mut i_frame_variants: Vec<IFrameStreamInfo> = vec![];
+        let mut renditions: Vec<Rendition> = vec![];
+        let mut pending_stream_inf: Option<PendingStreamInf> = None;
+
+        let playlist_base_url = url.pathname();
+
+        let lines = playlist.lines();
+        for line in lines {
+            let str_line =
+                line.map_err(|e| MultivariantPlaylistParsingError::Io(e.to_string()))?;
+            if str_line.is_empty() {
+                continue;
+            } else if let Some(stripped) = str_line.strip_prefix("#EXT") {
+                let colon_idx = match stripped.find(':') {
+                    None => str_line.len(),
+                    Some(idx) => idx + 4,
+                };
+                match &str_line[4..colon_idx] {
+                    "-X-INDEPENDENT-SEGMENTS" => independent_segments = true,
+                    "-X-STREAM-INF" => {
+                        pending_stream_inf = Some(parse_stream_inf_attribute_list(
+                            &str_line,
+                            colon_idx + 1,
+                        )?);
+                    }
+                    "-X-I-FRAME-STREAM-INF" => {
+                        let info = parse_i_frame_stream_inf_attribute_list(
+                            &str_line,
+                            colon_idx + 1,
+                            playlist_base_url,
+                        )?;
+                        i_frame_variants.push(info);
+                    }
+                    "-X-MEDIA" => {
+                        let rendition = parse_media_attribute_list(
+                            &str_line,
+                            colon_idx + 1,
+                            playlist_base_url,
+                        )?;
+                        renditions.push(rendition);
+                    }
+                    "M3U" => {}
+                    x => Logger::debug(&format!("Unrecognized tag: \"{}\"", x)),
+                }
+            } else if str_line.starts_with('#') {
+                continue;
+            } else {
+                // URI, always following the #EXT-X-STREAM-INF it applies to.
+                let variant_url = Url::new(str_line);
+                let variant_url = if variant_url.is_absolute() {
+                    variant_url
+                } else {
+                    Url::from_relative(playlist_base_url, variant_url)
+                };
+                match pending_stream_inf.take() {
+                    Some(pending) => variants.push(pending.into_variant_stream(variant_url)),
+                    None => return Err(MultivariantPlaylistParsingError::UriMissingForStreamInf),
+                }
+            }
+        }
+
+        Ok(MultivariantPlaylist {
+            independent_segments,
+            variants,
+            i_frame_variants,
+            renditions,
+            url,
+        })
+    }
+
+    /// Pick the `VariantStream` with the highest `BANDWIDTH` not exceeding
+    /// `max_bandwidth`, falling back to the lowest-bandwidth variant when
+    /// every one of them is above that ceiling.
+    ///
+    /// The foundation for adaptive bitrate switching: called again as the
+    /// estimated available bandwidth changes.
+    pub fn variant_by_bandwidth(&self, max_bandwidth: u64) -> Option<&VariantStream> {
+        self.variants
+            .iter()
+            .filter(|v| v.bandwidth <= max_bandwidth)
+            .max_by_key(|v| v.bandwidth)
+            .or_else(|| self.variants.iter().min_by_key(|v| v.bandwidth))
+    }
+
+    /// Return every `Rendition` of the given `rendition_type` belonging to
+    /// `group_id` whose `LANGUAGE` matches `language`.
+    pub fn renditions_by_language(
+        &self,
+        rendition_type: RenditionType,
+        group_id: &str,
+        language: &str,
+    ) -> Vec<&Rendition> {
+        self.renditions
+            .iter()
+            .filter(|r| {
+                r.rendition_type == rendition_type
+                    && r.group_id == group_id
+                    && r.language.as_deref() == Some(language)
+            })
+            .collect()
+    }
+}
+
+/// Attributes accumulated while parsing a `#EXT-X-STREAM-INF` tag, held onto
+/// until the URI line that necessarily follows it is reached.
+struct PendingStreamInf {
+    bandwidth: u64,
+    average_bandwidth: Option<u64>,
+    codecs: Option<String>,
+    resolution: Option<(u32, u32)>,
+    frame_rate: Option<f64>,
+    audio: Option<String>,
+    video: Option<String>,
+    subtitles: Option<String>,
+}
+
+impl PendingStreamInf {
+    fn into_variant_stream(self, url: Url) -> VariantStream {
+        VariantStream {
+            bandwidth: self.bandwidth,
+            average_bandwidth: self.average_bandwidth,
+            codecs: self.codecs,
+            resolution: self.resolution,
+            frame_rate: self.frame_rate,
+            audio: self.audio,
+            video: self.video,
+            subtitles: self.subtitles,
+            url,
+        }
+    }
+}
+
+fn parse_stream_inf_attribute_list(
+    str_line: &str,
+    mut base_offset: usize,
+) -> Result<PendingStreamInf, MultivariantPlaylistParsingError> {
+    let mut bandwidth: Option<u64> = None;
+    let mut average_bandwidth: Option<u64> = None;
+    let mut codecs: Option<String> = None;
+    let mut resolution: Option<(u32, u32)> = None;
+    let mut frame_rate: Option<f64> = None;
+    let mut audio: Option<String> = None;
+    let mut video: Option<String> = None;
+    let mut subtitles: Option<String> = None;
+
+    loop {
+        if base_offset >= str_line.len() {
+            break;
+        }
+        match str_line[base_offset..].find('=') {
+            None => break,
+            Some(idx) => match &str_line[base_offset..base_offset + idx] {
+                "BANDWIDTH" => {
+                    let (val, end_offset) = parse_decimal_integer(str_line, base_offset + idx + 1);
+                    base_offset = end_offset + 1;
+                    match val {
+                        Ok(v) => bandwidth = Some(v as u64),
+                        Err(_) => return Err(MultivariantPlaylistParsingError::UnparsableStreamInf),
+                    }
+                }
+                "AVERAGE-BANDWIDTH" => {
+                    let (val, end_offset) = parse_decimal_integer(str_line, base_offset + idx + 1);
+                    base_offset = end_offset + 1;
+                    if let Ok(v) = val {
+                        average_bandwidth = Some(v as u64);
+                    }
+                }
+                "CODECS" => {
+                    let (parsed, end_offset) = parse_quoted_string(str_line, base_offset + idx + 1);
+                    base_offset = end_offset + 1;
+                    if let Ok(val) = parsed {
+                        codecs = Some(val.to_owned());
+                    }
+                }
+                "RESOLUTION" => {
+                    let (val, end_offset) =
+                        parse_enumerated_string(str_line, base_offset + idx + 1);
+                    base_offset = end_offset + 1;
+                    resolution = parse_resolution(val);
+                }
+                "FRAME-RATE" => {
+                    let (val, end_offset) =
+                        parse_decimal_floating_point(str_line, base_offset + idx + 1);
+                    base_offset = end_offset + 1;
+                    if let Ok(v) = val {
+                        frame_rate = Some(v);
+                    }
+                }
+                "AUDIO" => {
+                    let (parsed, end_offset) = parse_quoted_string(str_line, base_offset + idx + 1);
+                    base_offset = end_offset + 1;
+                    if let Ok(val) = parsed {
+                        audio = Some(val.to_owned());
+                    }
+                }
+                "VIDEO" => {
+                    let (parsed, end_offset) = parse_quoted_string(str_line, base_offset + idx + 1);
+                    base_offset = end_offset + 1;
+                    if let Ok(val) = parsed {
+                        video = Some(val.to_owned());
+                    }
+                }
+                "SUBTITLES" => {
+                    let (parsed, end_offset) = parse_quoted_string(str_line, base_offset + idx + 1);
+                    base_offset = end_offset + 1;
+                    if let Ok(val) = parsed {
+                        subtitles = Some(val.to_owned());
+                    }
+                }
+                _ => {
+                    base_offset = skip_unknown_attribute_value(str_line, base_offset + idx + 1);
+                }
+            },
+        }
+    }
+
+    let bandwidth = bandwidth.ok_or(MultivariantPlaylistParsingError::UnparsableStreamInf)?;
+    Ok(PendingStreamInf {
+        bandwidth,
+        average_bandwidth,
+        codecs,
+        resolution,
+        frame_rate,
+        audio,
+        video,
+        subtitles,
+    })
+}
+
+fn parse_i_frame_stream_inf_attribute_list(
+    str_line: &str,
+    mut base_offset: usize,
+    playlist_base_url: &str,
+) -> Result<IFrameStreamInfo, MultivariantPlaylistParsingError> {
+    let mut bandwidth: Option<u64> = None;
+    let mut average_bandwidth: Option<u64> = None;
+    let mut codecs: Option<String> = None;
+    let mut resolution: Option<(u32, u32)> = None;
+    let mut uri: Option<Url> = None;
+
+    loop {
+        if base_offset >= str_line.len() {
+            break;
+        }
+        match str_line[base_offset..].find('=') {
+            None => break,
+            Some(idx) => match &str_line[base_offset..base_offset + idx] {
+                "BANDWIDTH" => {
+                    let (val, end_offset) = parse_decimal_integer(str_line, base_offset + idx + 1);
+                    base_offset = end_offset + 1;
+                    match val {
+                        Ok(v) => bandwidth = Some(v as u64),
+                        Err(_) => {
+                            return Err(MultivariantPlaylistParsingError::UnparsableIFrameStreamInf)
+                        }
+                    }
+                }
+                "AVERAGE-BANDWIDTH" => {
+                    let (val, end_offset) = parse_decimal_integer(str_line, base_offset + idx + 1);
+                    base_offset = end_offset + 1;
+                    if let Ok(v) = val {
+                        average_bandwidth = Some(v as u64);
+                    }
+                }
+                "CODECS" => {
+                    let (parsed, end_offset) = parse_quoted_string(str_line, base_offset + idx + 1);
+                    base_offset = end_offset + 1;
+                    if let Ok(val) = parsed {
+                        codecs = Some(val.to_owned());
+                    }
+                }
+                "RESOLUTION" => {
+                    let (val, end_offset) =
+                        parse_enumerated_string(str_line, base_offset + idx + 1);
+                    base_offset = end_offset + 1;
+                    resolution = parse_resolution(val);
+                }
+                "URI" => {
+                    let (parsed, end_offset) = parse_quoted_string(str_line, base_offset + idx + 1);
+                    base_offset = end_offset + 1;
+                    if let Ok(val) = parsed {
+                        let i_frame_url = Url::new(val.to_owned());
+                        let i_frame_url = if i_frame_url.is_absolute() {
+                            i_frame_url
+                        } else {
+                            Url::from_relative(playlist_base_url, i_frame_url)
+                        };
+                        uri = Some(i_frame_url);
+                    }
+                }
+                _ => {
+                    base_offset = skip_unknown_attribute_value(str_line, base_offset + idx + 1);
+                }
+            },
+        }
+    }
+
+    let bandwidth = bandwidth.ok_or(MultivariantPlaylistParsingError::UnparsableIFrameStreamInf)?;
+    let url = uri.ok_or(MultivariantPlaylistParsingError::UnparsableIFrameStreamInf)?;
+    Ok(IFrameStreamInfo {
+        bandwidth,
+        average_bandwidth,
+        codecs,
+        resolution,
+        url,
+    })
+}
+
+fn parse_media_attribute_list(
+    str_line: &str,
+    mut base_offset: usize,
+    playlist_base_url: &str,
+) -> Result<Rendition, MultivariantPlaylistParsingError> {
+    let mut rendition_type: Option<RenditionType> = None;
+    let mut group_id: Option<String> = None;
+    let mut name: Option<String> = None;
+    let mut language: Option<String> = None;
+    let mut is_default = false;
+    let mut autoselect = false;
+    let mut uri: Option<Url> = None;
+
+    loop {
+        if base_offset >= str_line.len() {
+            break;
+        }
+        match str_line[base_offset..].find('=') {
+            None => break,
+            Some(idx) => match &str_line[base_offset..base_offset + idx] {
+                "TYPE" => {
+                    let (val, end_offset) =
+                        parse_enumerated_string(str_line, base_offset + idx + 1);
+                    base_offset = end_offset + 1;
+                    rendition_type = Some(match val {
+                        "AUDIO" => RenditionType::Audio,
+                        "VIDEO" => RenditionType::Video,
+                        "SUBTITLES" => RenditionType::Subtitles,
+                        "CLOSED-CAPTIONS" => RenditionType::ClosedCaptions,
+                        x => {
+                            Logger::warn(&format!("Unrecognized MEDIA type: {}", x));
+                            return Err(MultivariantPlaylistParsingError::UnparsableMedia);
+                        }
+                    });
+                }
+                "GROUP-ID" => {
+                    let (parsed, end_offset) = parse_quoted_string(str_line, base_offset + idx + 1);
+                    base_offset = end_offset + 1;
+                    if let Ok(val) = parsed {
+                        group_id = Some(val.to_owned());
+                    }
+                }
+                "NAME" => {
+                    let (parsed, end_offset) = parse_quoted_string(str_line, base_offset + idx + 1);
+                    base_offset = end_offset + 1;
+                    if let Ok(val) = parsed {
+                        name = Some(val.to_owned());
+                    }
+                }
+                "LANGUAGE" => {
+                    let (parsed, end_offset) = parse_quoted_string(str_line, base_offset + idx + 1);
+                    base_offset = end_offset + 1;
+                    if let Ok(val) = parsed {
+                        language = Some(val.to_owned());
+                    }
+                }
+                "DEFAULT" => {
+                    let (val, end_offset) =
+                        parse_enumerated_string(str_line, base_offset + idx + 1);
+                    base_offset = end_offset + 1;
+                    is_default = val == "YES";
+                }
+                "AUTOSELECT" => {
+                    let (val, end_offset) =
+                        parse_enumerated_string(str_line, base_offset + idx + 1);
+                    base_offset = end_offset + 1;
+                    autoselect = val == "YES";
+                }
+                "URI" => {
+                    let (parsed, end_offset) = parse_quoted_string(str_line, base_offset + idx + 1);
+                    base_offset = end_offset + 1;
+                    if let Ok(val) = parsed {
+                        let media_url = Url::new(val.to_owned());
+                        let media_url = if media_url.is_absolute() {
+                            media_url
+                        } else {
+                            Url::from_relative(playlist_base_url, media_url)
+                        };
+                        uri = Some(media_url);
+                    }
+                }
+                _ => {
+                    base_offset = skip_unknown_attribute_value(str_line, base_offset + idx + 1);
+                }
+            },
+        }
+    }
+
+    let rendition_type =
+        rendition_type.ok_or(MultivariantPlaylistParsingError::MissingAttributeInMedia("TYPE"))?;
+    let group_id =
+        group_id.ok_or(MultivariantPlaylistParsingError::MissingAttributeInMedia("GROUP-ID"))?;
+    let name = name.ok_or(MultivariantPlaylistParsingError::MissingAttributeInMedia("NAME"))?;
+    Ok(Rendition {
+        rendition_type,
+        group_id,
+        name,
+        language,
+        is_default,
+        autoselect,
+        uri,
+    })
+}
+
+fn parse_resolution(val: &str) -> Option<(u32, u32)> {
+    let (width, height) = val.split_once('x')?;
+    Some((width.parse().ok()?, height.parse().ok()?))
+}
+
+/// Whether a fetched playlist turns out to be a Multivariant Playlist (one
+/// referencing other playlists) or a Media Playlist (one listing segments),
+/// as told apart by its first meaningful tag.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PlaylistKind {
+    Multivariant,
+    Media,
+}
+
+/// Peek at `playlist` to tell a Multivariant Playlist from a Media Playlist
+/// apart, without fully parsing it.
+///
+/// `#EXT-X-STREAM-INF`, `#EXT-X-I-FRAME-STREAM-INF` and `#EXT-X-MEDIA` only
+/// ever appear in a Multivariant Playlist, while `#EXTINF` and
+/// `#EXT-X-TARGETDURATION` only ever appear in a Media one; the first of
+/// either family encountered settles it. Returns `None` when neither family
+/// was found before `playlist` was exhausted, or a line could not be read.
+pub fn detect_playlist_kind(playlist: impl BufRead) -> Option<PlaylistKind> {
+    for line in playlist.lines() {
+        let str_line = line.ok()?;
+        match str_line.as_str() {
+            x if x.starts_with("#EXT-X-STREAM-INF")
+                || x.starts_with("#EXT-X-I-FRAME-STREAM-INF")
+                || x.starts_with("#EXT-X-MEDIA:") =>
+            {
+                return Some(PlaylistKind::Multivariant);
+            }
+            x if x.starts_with("#EXTINF") || x.starts_with("#EXT-X-TARGETDURATION") => {
+                return Some(PlaylistKind::Media);
+            }
+            _ => continue,
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn playlist_url() -> Url {
+        Url::new("http://example.com/hls/master.m3u8".to_owned())
+    }
+
+    #[test]
+    fn parses_stream_inf_with_interleaved_unrecognized_attribute() {
+        let playlist = "#EXTM3U\n\
+#EXT-X-STREAM-INF:BANDWIDTH=1280000,VENDOR-X=foo,CODECS=\"avc1.4d401f,mp4a.40.2\"\n\
+low.m3u8\n";
+        let multivariant = MultivariantPlaylist::create(playlist.as_bytes(), playlist_url()).unwrap();
+        assert_eq!(multivariant.variants.len(), 1);
+        assert_eq!(multivariant.variants[0].bandwidth, 1280000);
+        assert_eq!(
+            multivariant.variants[0].codecs.as_deref(),
+            Some("avc1.4d401f,mp4a.40.2")
+        );
+    }
+
+    #[test]
+    fn picks_the_highest_bandwidth_variant_under_the_ceiling() {
+        let playlist = "#EXTM3U\n\
+#EXT-X-STREAM-INF:BANDWIDTH=500000\n\
+low.m3u8\n\
+#EXT-X-STREAM-INF:BANDWIDTH=1500000\n\
+mid.m3u8\n\
+#EXT-X-STREAM-INF:BANDWIDTH=3000000\n\
+high.m3u8\n";
+        let multivariant = MultivariantPlaylist::create(playlist.as_bytes(), playlist_url()).unwrap();
+        let chosen = multivariant.variant_by_bandwidth(2000000).unwrap();
+        assert_eq!(chosen.bandwidth, 1500000);
+    }
+
+    #[test]
+    fn falls_back_to_the_lowest_bandwidth_variant_when_all_exceed_the_ceiling() {
+        let playlist = "#EXTM3U\n\
+#EXT-X-STREAM-INF:BANDWIDTH=1500000\n\
+mid.m3u8\n\
+#EXT-X-STREAM-INF:BANDWIDTH=3000000\n\
+high.m3u8\n";
+        let multivariant = MultivariantPlaylist::create(playlist.as_bytes(), playlist_url()).unwrap();
+        let chosen = multivariant.variant_by_bandwidth(100).unwrap();
+        assert_eq!(chosen.bandwidth, 1500000);
+    }
+
+    #[test]
+    fn detects_a_multivariant_playlist_from_its_stream_inf_tag() {
+        let playlist = "#EXTM3U\n#EXT-X-STREAM-INF:BANDWIDTH=500000\nlow.m3u8\n";
+        assert_eq!(
+            detect_playlist_kind(playlist.as_bytes()),
+            Some(PlaylistKind::Multivariant)
+        );
+    }
+
+    #[test]
+    fn detects_a_media_playlist_from_its_targetduration_tag() {
+        let playlist = "#EXTM3U\n#EXT-X-TARGETDURATION:6\n#EXTINF:6.0,\nseg0.ts\n";
+        assert_eq!(
+            detect_playlist_kind(playlist.as_bytes()),
+            Some(PlaylistKind::Media)
+        );
+    }
+}